@@ -0,0 +1,43 @@
+//! Helpers for killing a child process by pid.
+//!
+//! `Child`'s reaper thread owns the underlying `std::process::Child`, so
+//! `Child::kill` (and the kill-on-drop behavior) can't go through
+//! `std::process::Child::kill` directly. Instead we signal the process by
+//! pid, and treat "it's already gone" as success rather than an error --
+//! that race is the normal case when `kill` and the reaper overlap.
+
+use std::io;
+
+#[cfg(unix)]
+pub fn kill(pid: u32) -> io::Result<()> {
+    let ret = unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+    if ret == 0 {
+        return Ok(());
+    }
+    match io::Error::last_os_error() {
+        ref e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+        e => Err(e),
+    }
+}
+
+#[cfg(windows)]
+pub fn kill(pid: u32) -> io::Result<()> {
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+    use winapi::um::winnt::PROCESS_TERMINATE;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, FALSE, pid);
+        if handle.is_null() {
+            // The process is most likely already gone.
+            return Ok(());
+        }
+        let ret = TerminateProcess(handle, 1);
+        CloseHandle(handle);
+        if ret == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,390 @@
+//! An implementation of asynchronous process management for Tokio.
+//!
+//! This crate provides a [`CommandExt`] trait which extends the
+//! `std::process::Command` builder with `spawn_async`/`status_async`
+//! constructors that return a [`Child`] whose stdio handles (when piped)
+//! implement `tokio_io::AsyncRead`/`AsyncWrite`, and whose exit status can
+//! be awaited as a `Future` without blocking the reactor.
+
+extern crate futures;
+extern crate mio;
+extern crate tokio_codec;
+extern crate tokio_io;
+extern crate tokio_reactor;
+
+#[cfg(unix)]
+extern crate libc;
+#[cfg(unix)]
+extern crate tokio_uds;
+#[cfg(windows)]
+extern crate mio_named_pipes;
+#[cfg(windows)]
+extern crate winapi;
+
+mod codec;
+mod kill;
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+use unix as imp;
+#[cfg(windows)]
+use windows as imp;
+
+pub use codec::LengthDelimitedConfig;
+pub use imp::{ChildStderr, ChildStdin, ChildStdout};
+
+use std::io;
+use std::process::{Command, ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use futures::task::Task;
+use futures::{task, Async, Future, Poll};
+use tokio_io::io::read_to_end;
+
+/// Extends `std::process::Command` with methods for spawning a child that
+/// runs on a Tokio reactor rather than blocking the calling thread.
+pub trait CommandExt {
+    /// Spawns the command, wrapping any piped stdio handles so they can be
+    /// driven asynchronously, and returns a handle for tracking the child.
+    fn spawn_async(&mut self) -> io::Result<Child>;
+
+    /// Spawns the command purely to observe its exit status.
+    ///
+    /// Any stdio pipes configured on the command are immediately closed on
+    /// the parent's side so the child can't block waiting on a pipe nobody
+    /// will ever read or write.
+    fn status_async(&mut self) -> io::Result<Child>;
+
+    /// Spawns the command with one end of a connected `UnixStream`
+    /// inherited at `child_fd`, returning the parent-side async
+    /// `UnixStream` alongside the spawned `Child`.
+    ///
+    /// Unlike stdin/stdout/stderr, this gives the child a single duplex
+    /// channel to run a full-duplex IPC protocol over, rather than juggling
+    /// separate pipes. The child-side descriptor is closed on the parent's
+    /// side immediately after spawning so it doesn't leak into the parent
+    /// process.
+    ///
+    /// Unix-only: there is no portable equivalent, and this is gated off
+    /// on Windows.
+    #[cfg(unix)]
+    fn socketpair_async(&mut self, child_fd: ::std::os::unix::io::RawFd) -> io::Result<(Child, tokio_uds::UnixStream)>;
+}
+
+impl CommandExt for Command {
+    fn spawn_async(&mut self) -> io::Result<Child> {
+        Child::spawn(self)
+    }
+
+    #[cfg(unix)]
+    fn socketpair_async(&mut self, child_fd: ::std::os::unix::io::RawFd) -> io::Result<(Child, tokio_uds::UnixStream)> {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream as StdUnixStream;
+        use std::os::unix::process::CommandExt as StdUnixCommandExt;
+
+        let (parent, child) = StdUnixStream::pair()?;
+        let child_raw_fd = child.as_raw_fd();
+
+        // Runs in the forked child, after `fork` but before `exec`: move
+        // our end of the pair onto `child_fd` (closing whatever was
+        // already there), then hand control back to `exec`.
+        unsafe {
+            self.pre_exec(move || {
+                if child_raw_fd != child_fd && libc::dup2(child_raw_fd, child_fd) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // `dup2` always clears `FD_CLOEXEC` on the new descriptor
+                // -- except when `old == new`, where it's a no-op and
+                // `child_fd` keeps whatever cloexec state it already had
+                // (set, since this fd came from `UnixStream::pair`). Clear
+                // it explicitly so the descriptor isn't closed out from
+                // under the child at `exec`.
+                if libc::fcntl(child_fd, libc::F_SETFD, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let spawned = self.spawn_async()?;
+        // The child inherited its own copy of `child`'s fd across `fork`;
+        // drop our copy so it doesn't leak into the parent.
+        drop(child);
+
+        parent.set_nonblocking(true)?;
+        let parent = tokio_uds::UnixStream::from_std(parent, &tokio_reactor::Handle::default())?;
+
+        Ok((spawned, parent))
+    }
+
+    fn status_async(&mut self) -> io::Result<Child> {
+        let mut child = self.spawn_async()?;
+        // Drop (and thus close) any piped stdio now: nothing is going to
+        // read or write them, and leaving them open could make the child
+        // block forever (e.g. a child reading from stdin until EOF).
+        *child.stdin() = None;
+        *child.stdout() = None;
+        *child.stderr() = None;
+        Ok(child)
+    }
+}
+
+enum ReaperState {
+    Running(Option<Task>),
+    Exited(ExitStatus),
+    Failed(io::ErrorKind, String),
+}
+
+struct Reaper {
+    state: Arc<Mutex<ReaperState>>,
+}
+
+impl Reaper {
+    fn spawn(child: std::process::Child) -> Reaper {
+        let state = Arc::new(Mutex::new(ReaperState::Running(None)));
+        let thread_state = state.clone();
+
+        // Tokio's reactor has no portable "notify me when this pid exits"
+        // primitive, so we park a dedicated thread in a blocking `wait()`
+        // and have it wake whichever task is polling us once it returns.
+        let mut child = child;
+        thread::Builder::new()
+            .name("tokio-process-reaper".to_owned())
+            .spawn(move || {
+                let result = child.wait();
+                let mut guard = thread_state.lock().unwrap();
+                let prev = std::mem::replace(
+                    &mut *guard,
+                    match result {
+                        Ok(status) => ReaperState::Exited(status),
+                        Err(ref e) => ReaperState::Failed(e.kind(), e.to_string()),
+                    },
+                );
+                if let ReaperState::Running(Some(task)) = prev {
+                    task.notify();
+                }
+            })
+            .expect("failed to spawn tokio-process reaper thread");
+
+        Reaper { state }
+    }
+
+    fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        match *self.state.lock().unwrap() {
+            ReaperState::Running(_) => Ok(None),
+            ReaperState::Exited(status) => Ok(Some(status)),
+            ReaperState::Failed(kind, ref msg) => Err(io::Error::new(kind, msg.clone())),
+        }
+    }
+
+    fn poll_wait(&self) -> Poll<ExitStatus, io::Error> {
+        let mut guard = self.state.lock().unwrap();
+        match *guard {
+            ReaperState::Exited(status) => Ok(Async::Ready(status)),
+            ReaperState::Failed(kind, ref msg) => Err(io::Error::new(kind, msg.clone())),
+            ReaperState::Running(ref mut task) => {
+                *task = Some(task::current());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+
+    fn running(&self) -> bool {
+        match *self.state.lock().unwrap() {
+            ReaperState::Running(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A running (or exited) child process, spawned asynchronously via
+/// [`CommandExt::spawn_async`] or [`CommandExt::status_async`].
+///
+/// `Child` itself implements `Future<Item = ExitStatus>`, resolving once
+/// the process has exited and been reaped.
+pub struct Child {
+    pid: u32,
+    kill_on_drop: bool,
+    reaper: Reaper,
+
+    stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
+}
+
+impl Child {
+    fn spawn(cmd: &mut Command) -> io::Result<Child> {
+        let mut child = cmd.spawn()?;
+        let pid = child.id();
+
+        let stdin = child.stdin.take().map(ChildStdin::new).transpose()?;
+        let stdout = child.stdout.take().map(ChildStdout::new).transpose()?;
+        let stderr = child.stderr.take().map(ChildStderr::new).transpose()?;
+
+        Ok(Child {
+            pid,
+            kill_on_drop: true,
+            reaper: Reaper::spawn(child),
+            stdin,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of this child while it is
+    /// still running, or `None` once it has exited and been reaped.
+    pub fn id(&self) -> Option<u32> {
+        if self.reaper.running() {
+            Some(self.pid)
+        } else {
+            None
+        }
+    }
+
+    /// The child's stdin, if it was configured with `Stdio::piped()`.
+    pub fn stdin(&mut self) -> &mut Option<ChildStdin> {
+        &mut self.stdin
+    }
+
+    /// The child's stdout, if it was configured with `Stdio::piped()`.
+    pub fn stdout(&mut self) -> &mut Option<ChildStdout> {
+        &mut self.stdout
+    }
+
+    /// The child's stderr, if it was configured with `Stdio::piped()`.
+    pub fn stderr(&mut self) -> &mut Option<ChildStderr> {
+        &mut self.stderr
+    }
+
+    /// Forces the child to exit.
+    ///
+    /// This only sends the kill signal; it does not wait for the process
+    /// to actually exit. That should be done by awaiting this `Child` (or
+    /// its [`wait`](Child::wait)) after calling `kill`.
+    pub fn kill(&mut self) -> io::Result<()> {
+        kill::kill(self.pid)
+    }
+
+    /// Checks whether the child has exited, without blocking or consuming
+    /// this handle.
+    ///
+    /// Returns `Ok(None)` if the child is still running. Once it returns
+    /// `Ok(Some(status))`, it keeps returning that same cached status on
+    /// every later call (matching `std::process::Child::try_wait`), and a
+    /// subsequent `await` on this `Child` resolves immediately.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.reaper.try_wait()
+    }
+
+    /// Returns a future that resolves to the child's exit status, borrowing
+    /// `self` rather than consuming it so that `stdin`/`stdout`/`stderr`
+    /// remain accessible while the wait is in flight. Polling it never
+    /// closes the child's pipe handles.
+    ///
+    /// Shares its cached exit status with `try_wait`: once either has
+    /// observed the child exit, both keep reporting that status cheaply.
+    pub fn wait(&mut self) -> Wait {
+        Wait { child: self }
+    }
+
+    /// Drains the child's stdout/stderr (if piped) and waits for it to
+    /// exit, returning the collected `Output`.
+    pub fn wait_with_output(mut self) -> WaitWithOutput {
+        // Drop stdin (closing it) before waiting: a child reading its
+        // stdin to EOF (e.g. `cat`) would otherwise never exit, hanging
+        // this future forever, just as `std::process::Child::wait_with_output`
+        // closes stdin for the same reason.
+        self.stdin = None;
+        let stdout = self.stdout.take();
+        let stderr = self.stderr.take();
+        WaitWithOutput {
+            child: self,
+            reads: Some(Box::new(read_to_end_opt(stdout).join(read_to_end_opt(stderr)))),
+            reads_done: None,
+        }
+    }
+}
+
+impl Future for Child {
+    type Item = ExitStatus;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<ExitStatus, io::Error> {
+        self.reaper.poll_wait()
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        // Only kill while still running: once `reaper` has observed the
+        // child exit, `self.pid` may already have been recycled by the OS
+        // for an unrelated process.
+        if self.kill_on_drop && self.reaper.running() {
+            let _ = self.kill();
+        }
+    }
+}
+
+/// A future returned by [`Child::wait`], resolving to the child's
+/// `ExitStatus` without taking ownership of the `Child`.
+pub struct Wait<'a> {
+    child: &'a mut Child,
+}
+
+impl<'a> Future for Wait<'a> {
+    type Item = ExitStatus;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<ExitStatus, io::Error> {
+        self.child.reaper.poll_wait()
+    }
+}
+
+/// A future returned by [`Child::wait_with_output`].
+pub struct WaitWithOutput {
+    child: Child,
+    reads: Option<Box<Future<Item = (Vec<u8>, Vec<u8>), Error = io::Error>>>,
+    reads_done: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Future for WaitWithOutput {
+    type Item = Output;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Output, io::Error> {
+        if self.reads_done.is_none() {
+            match self.reads.as_mut().expect("polled after completion").poll()? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(pair) => {
+                    self.reads_done = Some(pair);
+                    self.reads = None;
+                }
+            }
+        }
+
+        let status = match self.child.wait().poll()? {
+            Async::NotReady => return Ok(Async::NotReady),
+            Async::Ready(status) => status,
+        };
+        let (stdout, stderr) = self.reads_done.take().expect("polled after completion");
+        Ok(Async::Ready(Output {
+            status,
+            stdout,
+            stderr,
+        }))
+    }
+}
+
+fn read_to_end_opt<R>(io: Option<R>) -> Box<Future<Item = Vec<u8>, Error = io::Error>>
+where
+    R: tokio_io::AsyncRead + 'static,
+{
+    match io {
+        Some(io) => Box::new(read_to_end(io, Vec::new()).map(|(_, buf)| buf)),
+        None => Box::new(futures::future::ok(Vec::new())),
+    }
+}
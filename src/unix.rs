@@ -0,0 +1,159 @@
+//! Unix-specific pieces: non-blocking stdio pipes backed by raw fds.
+
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use futures::{Async, Poll};
+use mio::event::Evented;
+use mio::unix::EventedFd;
+use mio::{Poll as MioPoll, PollOpt, Ready, Token};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_reactor::PollEvented;
+
+fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn cvt(ret: libc::ssize_t) -> io::Result<usize> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// An owned `RawFd`, closed on `Drop`.
+///
+/// This is the value registered with the reactor (as `PollEvented<RawIo>`),
+/// so it must close the fd in its own `Drop` rather than have the owning
+/// `ChildStdin`/`ChildStdout`/`ChildStderr` close it directly: `PollEvented`
+/// deregisters from the reactor when *it* drops, and that has to happen
+/// before the fd is closed, or mio's `epoll_ctl(DEL)` can run against an
+/// already-closed (and potentially recycled) descriptor.
+struct RawIo(RawFd);
+
+impl Drop for RawIo {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+impl Evented for RawIo {
+    fn register(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &MioPoll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+macro_rules! pipe_type {
+    ($name:ident, $std:ty) => {
+        pub struct $name {
+            // Kept for the lifetime of the pipe rather than recreated per
+            // `poll`, so the reactor registration (and the waker it holds
+            // on a `NotReady` result) survives across calls instead of
+            // being torn down as soon as a single `read`/`write` returns.
+            io: PollEvented<RawIo>,
+        }
+
+        impl $name {
+            pub(crate) fn new(inner: $std) -> io::Result<Self> {
+                let fd = inner.as_raw_fd();
+                set_nonblocking(fd)?;
+                // We now own `fd` directly (closed by `RawIo`'s `Drop`), so
+                // don't let the std handle close it out from under us.
+                mem::forget(inner);
+                Ok($name {
+                    io: PollEvented::new(RawIo(fd)),
+                })
+            }
+
+            fn fd(&self) -> RawFd {
+                self.io.get_ref().0
+            }
+        }
+    };
+}
+
+pipe_type!(ChildStdin, ::std::process::ChildStdin);
+pipe_type!(ChildStdout, ::std::process::ChildStdout);
+pipe_type!(ChildStderr, ::std::process::ChildStderr);
+
+impl Write for ChildStdin {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Re-register the *current* task's waker on the persistent `io`
+        // handle every call, replacing whatever was registered before,
+        // instead of standing up a throwaway registration that forgets the
+        // waker the moment this function returns.
+        match self.io.poll_write_ready()? {
+            Async::NotReady => Err(io::ErrorKind::WouldBlock.into()),
+            Async::Ready(_) => match cvt(unsafe { libc::write(self.fd(), buf.as_ptr() as *const _, buf.len()) }) {
+                Ok(n) => Ok(n),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.io.clear_write_ready()?;
+                    Err(io::ErrorKind::WouldBlock.into())
+                }
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for ChildStdin {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+macro_rules! readable_pipe {
+    ($name:ident) => {
+        impl Read for $name {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                // See the note on `ChildStdin::write`: this re-registers
+                // the current task against the same long-lived `io`
+                // handle every call, so a `NotReady` here doesn't drop the
+                // registration the next readiness event needs to find.
+                match self.io.poll_read_ready(Ready::readable())? {
+                    Async::NotReady => Err(io::ErrorKind::WouldBlock.into()),
+                    Async::Ready(_) => {
+                        match cvt(unsafe { libc::read(self.fd(), buf.as_mut_ptr() as *mut _, buf.len()) }) {
+                            Ok(n) => Ok(n),
+                            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                                self.io.clear_read_ready(Ready::readable())?;
+                                Err(io::ErrorKind::WouldBlock.into())
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                }
+            }
+        }
+
+        impl AsyncRead for $name {}
+    };
+}
+
+readable_pipe!(ChildStdout);
+readable_pipe!(ChildStderr);
@@ -0,0 +1,67 @@
+//! Windows-specific pieces: async stdio pipes backed by named pipe handles.
+//!
+//! This mirrors `unix.rs`'s structure (a thin owning wrapper around the
+//! inherited pipe handle, registered with the reactor through `mio-named-pipes`)
+//! but is far less exercised than the Unix backend -- most development and
+//! testing of this crate happens on Unix.
+
+use std::io::{self, Read, Write};
+
+use futures::{Async, Poll};
+use mio_named_pipes::NamedPipe;
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_reactor::PollEvented;
+
+macro_rules! pipe_type {
+    ($name:ident, $std:ty) => {
+        pub struct $name {
+            io: PollEvented<NamedPipe>,
+        }
+
+        impl $name {
+            pub(crate) fn new(inner: $std) -> io::Result<Self> {
+                use std::os::windows::io::IntoRawHandle;
+
+                let pipe = unsafe { NamedPipe::from_raw_handle(inner.into_raw_handle()) };
+                Ok($name {
+                    io: PollEvented::new(pipe),
+                })
+            }
+        }
+    };
+}
+
+pipe_type!(ChildStdin, ::std::process::ChildStdin);
+pipe_type!(ChildStdout, ::std::process::ChildStdout);
+pipe_type!(ChildStderr, ::std::process::ChildStderr);
+
+impl Write for ChildStdin {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.io.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl AsyncWrite for ChildStdin {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+macro_rules! readable_pipe {
+    ($name:ident) => {
+        impl Read for $name {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.io.read(buf)
+            }
+        }
+
+        impl AsyncRead for $name {}
+    };
+}
+
+readable_pipe!(ChildStdout);
+readable_pipe!(ChildStderr);
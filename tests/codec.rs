@@ -0,0 +1,70 @@
+extern crate bytes;
+extern crate futures;
+extern crate tokio;
+extern crate tokio_process;
+
+use std::process::{Command, Stdio};
+
+use bytes::Bytes;
+use futures::future::Future;
+use futures::sink::Sink;
+use futures::stream::Stream;
+use tokio::executor::current_thread;
+use tokio_process::{CommandExt, LengthDelimitedConfig};
+
+fn cat() -> Command {
+    let mut cmd = Command::new("cat");
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+    cmd
+}
+
+#[test]
+/// `framed_lines` on stdin (encoder) and stdout (decoder) should round-trip
+/// a line through `cat`, and the decode stream should end cleanly (`None`,
+/// not an error) once the child's stdout closes at EOF.
+fn framed_lines_round_trip_and_signals_eof() {
+    let mut child = cat().spawn_async().unwrap();
+    let stdin = child.stdin().take().unwrap();
+    let stdout = child.stdout().take().unwrap();
+
+    let send = stdin.framed_lines().send("hello".to_string()).map(|sink| {
+        // Drop (and thus close) stdin once the line is flushed so `cat`
+        // sees EOF and the decoder below ends cleanly.
+        drop(sink);
+    });
+    let recv = stdout.framed_lines().collect();
+
+    let (_, lines) = current_thread::block_on_all(send.join(recv)).unwrap();
+    assert_eq!(lines, vec!["hello".to_string()]);
+}
+
+#[test]
+/// `framed_length_delimited` should round-trip a frame through `cat` with
+/// both the default (big-endian) and an explicitly little-endian length
+/// field, as long as both ends agree on the config.
+fn framed_length_delimited_round_trips_big_and_little_endian() {
+    for config in &[
+        LengthDelimitedConfig::new(),
+        LengthDelimitedConfig::new().little_endian(),
+    ] {
+        let mut child = cat().spawn_async().unwrap();
+        let stdin = child.stdin().take().unwrap();
+        let stdout = child.stdout().take().unwrap();
+
+        let payload = Bytes::from_static(b"ping");
+        let send = stdin
+            .framed_length_delimited(config)
+            .send(payload.clone())
+            .map(|_| ());
+        let recv = stdout
+            .framed_length_delimited(config)
+            .into_future()
+            .map(|(frame, _)| frame)
+            .map_err(|(e, _)| e);
+
+        let (_, frame) = current_thread::block_on_all(send.join(recv)).unwrap();
+        assert_eq!(&frame.unwrap()[..], &payload[..]);
+
+        drop(child);
+    }
+}
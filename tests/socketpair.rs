@@ -0,0 +1,37 @@
+#![cfg(unix)]
+
+extern crate futures;
+extern crate tokio;
+extern crate tokio_io;
+extern crate tokio_process;
+
+use std::os::unix::io::RawFd;
+use std::process::Command;
+
+use futures::future::Future;
+use tokio::executor::current_thread;
+use tokio_io::io::{read_exact, write_all};
+use tokio_process::CommandExt;
+
+const CHILD_FD: RawFd = 3;
+
+#[test]
+/// Checks that `socketpair_async` hands the child a working duplex channel
+/// independent of its stdin/stdout: a shell that bounces whatever it reads
+/// from fd 3 back to fd 3 should echo what we write over the returned
+/// `UnixStream`.
+fn socketpair_roundtrip() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(format!("cat <&{fd} >&{fd}", fd = CHILD_FD));
+
+    let (child, stream) = cmd.socketpair_async(CHILD_FD).expect("failed to spawn child");
+
+    let roundtrip = write_all(stream, b"ping".to_vec())
+        .and_then(|(stream, _)| read_exact(stream, [0u8; 4]))
+        .map(|(_, buf)| buf);
+
+    let buf = current_thread::block_on_all(roundtrip).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    drop(child);
+}
@@ -8,6 +8,7 @@ extern crate env_logger;
 
 use std::io;
 use std::process::{Stdio, ExitStatus, Command};
+use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -122,6 +123,94 @@ fn wait_with_output_captures() {
     assert_eq!(output.stderr.len(), 0);
 }
 
+#[test]
+fn wakeups_survive_not_ready() {
+    // Regression test: the first poll of `read_until` happens before `cat`
+    // has been given anything to echo, so it must return `NotReady` while
+    // still registering a waker that fires once the delayed write below
+    // lands. If the stdio wrapper dropped that registration instead of
+    // keeping it across polls, the notification would never arrive and
+    // this test would hang until the deadline fires.
+    let mut child = cat().spawn_async().unwrap();
+    let stdin = child.stdin().take().unwrap();
+    let stdout = child.stdout().take().unwrap();
+
+    let delayed_write = tokio::timer::Delay::new(Instant::now() + Duration::from_millis(200))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .and_then(move |_| write_all(stdin, b"hello\n".to_vec()));
+
+    let read_line = read_until(io::BufReader::new(stdout), b'\n', Vec::new());
+
+    let future = delayed_write.join(read_line).map(|(_, (_, line))| line);
+
+    let mut rt = tokio::runtime::current_thread::Runtime::new().unwrap();
+    let line = rt
+        .block_on(Deadline::new(future, Instant::now() + Duration::from_secs(5)))
+        .expect("timed out -- a lost waker would stall the read forever");
+    assert_eq!(line, b"hello\n");
+}
+
+#[test]
+fn wait_borrows_and_is_idempotent_with_try_wait() {
+    let mut child = cat().spawn_async().unwrap();
+    let stdin = child.stdin().take().unwrap();
+    let stdout = child.stdout().take().unwrap();
+
+    // Closing stdin makes `cat` see EOF and exit, while stdout is drained
+    // concurrently with `wait()` below. `wait()` only borrows `child`, which
+    // is why `child` can still be used after it resolves (see below).
+    drop(stdin);
+    let drain = read_to_end(stdout, Vec::new());
+
+    let status = current_thread::block_on_all(child.wait().join(drain).map(|(status, _)| status)).unwrap();
+    assert!(status.success());
+
+    // Idempotent with `try_wait`: both share the same cached exit status.
+    assert_eq!(child.try_wait().unwrap(), Some(status));
+    assert_eq!(
+        current_thread::block_on_all(child.wait()).unwrap().code(),
+        status.code()
+    );
+}
+
+#[test]
+fn try_wait_and_id_observe_reap() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg("sleep 0.2");
+    let mut child = cmd.spawn_async().expect("failed to spawn child");
+
+    assert!(child.id().is_some(), "id() should report a pid while running");
+    assert_eq!(child.try_wait().unwrap(), None);
+
+    // Poll until the child has actually exited, bounded so a regression
+    // can't hang the suite forever.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "child was never reaped");
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert!(status.success());
+
+    // Fused: once exited, repeated calls keep returning the same cached
+    // status rather than erroring or blocking.
+    assert_eq!(child.try_wait().unwrap(), Some(status));
+    assert_eq!(child.try_wait().unwrap(), Some(status));
+
+    // Reaped -> no more live pid.
+    assert_eq!(child.id(), None);
+
+    // `try_wait` and `poll` share the same cached exit status, so awaiting
+    // the child now must resolve immediately rather than parking.
+    let mut rt = tokio::runtime::current_thread::Runtime::new().unwrap();
+    let resolved = rt
+        .block_on(Deadline::new(child, Instant::now() + Duration::from_millis(100)))
+        .expect("await after try_wait observed exit should resolve immediately");
+    assert_eq!(resolved.code(), status.code());
+}
+
 #[test]
 fn status_closes_any_pipes() {
     // Cat will open a pipe between the parent and child.